@@ -7,13 +7,25 @@ use substreams_ethereum::pb::eth::v2 as eth;
 use substreams_ethereum::Event;
 
 // === [ A D D I T I O N S ] ===
-use substreams::store::{StoreAdd, StoreAddBigInt, StoreGet, StoreGetBigInt, StoreNew, StoreSet, StoreSetRaw};
+// NOTE: this module alone is not deployable yet. It reads/writes proto fields
+// (UniTransfer.fee_paid_wei, UniTransfer.tier) and entity fields
+// (Wallet.netValue/percentageOfSupply/totalFeesPaidWei, Delegate, Delegation,
+// WhaleTransfer.tier/feePaidWei) that must be added to this crate's .proto
+// and the subgraph's schema.graphql, and declares new store/map handlers
+// (store_wallet_fees, store_delegate_voting_power, store_delegate_targets,
+// store_delegator_counts, store_circulating_supply, plus the `params` input
+// threaded through map_events/map_calls/map_whale_transfers/graph_out and
+// graph_out's new store inputs) that must be wired into substreams.yaml as module
+// inputs before this will build or index. None of those companion files are
+// in this tree; land them alongside whichever commit depends on them.
+use substreams::store::{StoreAdd, StoreAddBigInt, StoreGet, StoreGetBigInt, StoreNew, StoreSet, StoreSetBigInt, StoreSetRaw};
 use substreams::store::pb::bigint::BigInt as StoreBigInt; // Alias for the store BigInt
 
 
 // Crate used for creating the GraphQL entities
 #[allow(unused_imports)]
 use num_traits::cast::ToPrimitive;
+use std::collections::HashMap;
 use std::str::FromStr;
 use substreams::scalar::BigDecimal;
 use substreams_entity_change::pb::entity::EntityChanges;
@@ -23,18 +35,127 @@ use lazy_static::lazy_static;
 substreams_ethereum::init!();
 
 const UNI_TRACKED_CONTRACT: [u8; 20] = hex!("1f9840a85d5af5bf1d1762f925bdaddc4201f984");
+// Single key under which store_circulating_supply keeps its one running total.
+const CIRCULATING_SUPPLY_KEY: &str = "circulating_supply";
+const ZERO_ADDRESS: [u8; 20] = [0u8; 20];
 
 lazy_static! {
     static ref WHALE_THRESHOLD: num_bigint::BigInt = {
 	"10000000000000000000000".parse().unwrap()
 	};
 }
-fn map_uni_events(blk: &eth::Block, events: &mut contract::Events) {
+
+// A single whale tier: transfers at or above `min_amount` (and below the next
+// tier's cutoff) are labelled `label`.
+struct WhaleTier {
+    label: String,
+    min_amount: BigInt,
+}
+
+// Module configuration decoded from the substreams manifest `params` string,
+// so the same binary can be repointed at any ERC20-compatible governance
+// token/deployment without recompiling. Expected format:
+//   "contract=0x...;thresholds=<amount>:<label>,<amount>:<label>,..."
+// Both fields are optional; missing ones fall back to the UNI defaults below.
+struct ModuleConfig {
+    tracked_contract: Vec<u8>,
+    // Ascending by min_amount.
+    whale_tiers: Vec<WhaleTier>,
+}
+
+fn default_whale_tiers() -> Vec<WhaleTier> {
+    vec![
+        WhaleTier { label: "shrimp".to_string(), min_amount: BigInt::from(0u8) },
+        WhaleTier { label: "dolphin".to_string(), min_amount: BigInt::from_str("1000000000000000000000").unwrap() },
+        WhaleTier { label: "whale".to_string(), min_amount: WHALE_THRESHOLD.clone() },
+    ]
+}
+
+fn parse_params(params: &str) -> ModuleConfig {
+    let mut tracked_contract = UNI_TRACKED_CONTRACT.to_vec();
+    let mut whale_tiers: Vec<WhaleTier> = Vec::new();
+
+    for field in params.split(';') {
+        let field = field.trim();
+        if let Some(value) = field.strip_prefix("contract=") {
+            if let Ok(bytes) = Hex::decode(value.trim_start_matches("0x")) {
+                // An address of the wrong length would never match any log/call
+                // and silently zero out the whole module, so ignore it and keep
+                // the default instead.
+                if bytes.len() == 20 {
+                    tracked_contract = bytes;
+                }
+            }
+        } else if let Some(value) = field.strip_prefix("thresholds=") {
+            for tier in value.split(',') {
+                if let Some((amount, label)) = tier.split_once(':') {
+                    if let Ok(min_amount) = BigInt::from_str(amount.trim()) {
+                        whale_tiers.push(WhaleTier { label: label.trim().to_string(), min_amount });
+                    }
+                }
+            }
+        }
+    }
+
+    if whale_tiers.is_empty() {
+        whale_tiers = default_whale_tiers();
+    } else {
+        whale_tiers.sort_by(|a, b| a.min_amount.cmp(&b.min_amount));
+    }
+
+    ModuleConfig { tracked_contract, whale_tiers }
+}
+
+// Classifies `amount` against the ordered tier cutoffs, returning the label
+// of the highest tier it clears (defaulting to the lowest tier's label).
+fn classify_tier(amount: &BigInt, tiers: &[WhaleTier]) -> String {
+    // Default to the lowest configured tier's own label rather than a
+    // hardcoded literal like "shrimp" — a custom `thresholds=` config may not
+    // use that label at all. Configs are expected to include a 0-floor tier;
+    // `parse_params` falls back to `default_whale_tiers()` (which has one)
+    // whenever no thresholds are supplied.
+    let mut label = tiers.first().map(|t| t.label.clone()).unwrap_or_else(|| "shrimp".to_string());
+
+    for tier in tiers {
+        if amount.ge(&tier.min_amount) {
+            label = tier.label.clone();
+        } else {
+            break;
+        }
+    }
+
+    label
+}
+
+// Builds a tx hash -> fee paid (in wei) lookup for the block, computed as
+// `gas_used * gas_price`. For EIP-1559 transactions the Firehose
+// `TransactionTrace.gas_price` field already holds the effective gas price,
+// so no base-fee reconstruction is needed here. Transactions whose root call
+// reverted are skipped so fees from failed calls aren't attributed to a
+// transfer that never happened.
+// Keyed by tx hash, value is (tx origin EOA, fee paid in wei). The fee is a
+// property of the whole transaction, not of any single log/call inside it,
+// so callers must attribute it once per tx hash, not once per decoded event.
+fn build_tx_fee_lookup(blk: &eth::Block) -> HashMap<Vec<u8>, (Vec<u8>, BigInt)> {
+    blk.transactions()
+        .filter(|tx| !tx.calls.iter().any(|call| call.index == 0 && call.state_reverted))
+        .map(|tx| {
+            let gas_used = BigInt::from(tx.gas_used);
+            let gas_price = tx.gas_price.as_ref()
+                .map(|price| BigInt::from_bytes_be(Sign::Plus, &price.bytes))
+                .unwrap_or_default();
+            (tx.hash.clone(), (tx.from.clone(), gas_used * gas_price))
+        })
+        .collect()
+}
+
+fn map_uni_events(blk: &eth::Block, events: &mut contract::Events, tracked_contract: &[u8]) {
+    let tx_fees = build_tx_fee_lookup(blk);
     events.uni_approvals.append(&mut blk
         .receipts()
         .flat_map(|view| {
             view.receipt.logs.iter()
-                .filter(|log| log.address == UNI_TRACKED_CONTRACT)
+                .filter(|log| log.address.as_slice() == tracked_contract)
                 .filter_map(|log| {
                     if let Some(event) = abi::uni_contract::events::Approval::match_and_decode(log) {
                         return Some(contract::UniApproval {
@@ -56,7 +177,7 @@ fn map_uni_events(blk: &eth::Block, events: &mut contract::Events) {
         .receipts()
         .flat_map(|view| {
             view.receipt.logs.iter()
-                .filter(|log| log.address == UNI_TRACKED_CONTRACT)
+                .filter(|log| log.address.as_slice() == tracked_contract)
                 .filter_map(|log| {
                     if let Some(event) = abi::uni_contract::events::DelegateChanged::match_and_decode(log) {
                         return Some(contract::UniDelegateChanged {
@@ -78,7 +199,7 @@ fn map_uni_events(blk: &eth::Block, events: &mut contract::Events) {
         .receipts()
         .flat_map(|view| {
             view.receipt.logs.iter()
-                .filter(|log| log.address == UNI_TRACKED_CONTRACT)
+                .filter(|log| log.address.as_slice() == tracked_contract)
                 .filter_map(|log| {
                     if let Some(event) = abi::uni_contract::events::DelegateVotesChanged::match_and_decode(log) {
                         return Some(contract::UniDelegateVotesChanged {
@@ -100,7 +221,7 @@ fn map_uni_events(blk: &eth::Block, events: &mut contract::Events) {
         .receipts()
         .flat_map(|view| {
             view.receipt.logs.iter()
-                .filter(|log| log.address == UNI_TRACKED_CONTRACT)
+                .filter(|log| log.address.as_slice() == tracked_contract)
                 .filter_map(|log| {
                     if let Some(event) = abi::uni_contract::events::MinterChanged::match_and_decode(log) {
                         return Some(contract::UniMinterChanged {
@@ -121,14 +242,20 @@ fn map_uni_events(blk: &eth::Block, events: &mut contract::Events) {
         .receipts()
         .flat_map(|view| {
             view.receipt.logs.iter()
-                .filter(|log| log.address == UNI_TRACKED_CONTRACT)
+                .filter(|log| log.address.as_slice() == tracked_contract)
                 .filter_map(|log| {
                     if let Some(event) = abi::uni_contract::events::Transfer::match_and_decode(log) {
-			
+
 			// **1. Get amount as Rust BigInt for comparison**
 			//let amount_str = event.amount.to_string();
 			//let amount_bigint = BigInt::from_str(&amount_str).unwrap_or_default();
 
+			// Informational only: the full tx fee. It is kept on the first
+			// tracked transfer of each tx and zeroed on the rest (see below).
+			// Wallet fee accounting attributes it once per tx to the tx
+			// origin in store_wallet_fees, not once per transfer here.
+			let fee_paid_wei = tx_fees.get(&view.transaction.hash).map(|(_, fee)| fee.clone()).unwrap_or_default();
+
 			return Some(contract::UniTransfer {
                            	evt_tx_hash: Hex(&view.transaction.hash).to_string(),
                             	evt_index: log.block_index,
@@ -137,19 +264,33 @@ fn map_uni_events(blk: &eth::Block, events: &mut contract::Events) {
                             	amount: event.amount.to_string(),
                             	from: event.from,
                             	to: event.to,
+                            	fee_paid_wei: fee_paid_wei.to_string(),
                         	});
                         }
                     None
                 })
         })
         .collect());
+
+    // feePaidWei reflects the enclosing tx's whole fee, so a tx with several
+    // tracked transfers would have it stamped on each one; summing the field
+    // across WhaleTransfer entities would then over-count the real fee N×.
+    // Attribute it to only the first tracked transfer per tx and zero it out
+    // on the rest. Wallet-level accounting is unaffected: store_wallet_fees
+    // already charges the tx origin once per tx, independent of this field.
+    let mut fee_charged_txs: std::collections::HashSet<String> = std::collections::HashSet::new();
+    for transfer in events.uni_transfers.iter_mut() {
+        if !fee_charged_txs.insert(transfer.evt_tx_hash.clone()) {
+            transfer.fee_paid_wei = "0".to_string();
+        }
+    }
 }
-fn map_uni_calls(blk: &eth::Block, calls: &mut contract::Calls) {
+fn map_uni_calls(blk: &eth::Block, calls: &mut contract::Calls, tracked_contract: &[u8]) {
     calls.uni_call_approves.append(&mut blk
         .transactions()
         .flat_map(|tx| {
             tx.calls.iter()
-                .filter(|call| call.address == UNI_TRACKED_CONTRACT && abi::uni_contract::functions::Approve::match_call(call))
+                .filter(|call| call.address.as_slice() == tracked_contract && abi::uni_contract::functions::Approve::match_call(call))
                 .filter_map(|call| {
                     match abi::uni_contract::functions::Approve::decode(call) {
                         Ok(decoded_call) => {
@@ -178,7 +319,7 @@ fn map_uni_calls(blk: &eth::Block, calls: &mut contract::Calls) {
         .transactions()
         .flat_map(|tx| {
             tx.calls.iter()
-                .filter(|call| call.address == UNI_TRACKED_CONTRACT && abi::uni_contract::functions::Delegate::match_call(call))
+                .filter(|call| call.address.as_slice() == tracked_contract && abi::uni_contract::functions::Delegate::match_call(call))
                 .filter_map(|call| {
                     match abi::uni_contract::functions::Delegate::decode(call) {
                         Ok(decoded_call) => {
@@ -200,7 +341,7 @@ fn map_uni_calls(blk: &eth::Block, calls: &mut contract::Calls) {
         .transactions()
         .flat_map(|tx| {
             tx.calls.iter()
-                .filter(|call| call.address == UNI_TRACKED_CONTRACT && abi::uni_contract::functions::DelegateBySig::match_call(call))
+                .filter(|call| call.address.as_slice() == tracked_contract && abi::uni_contract::functions::DelegateBySig::match_call(call))
                 .filter_map(|call| {
                     match abi::uni_contract::functions::DelegateBySig::decode(call) {
                         Ok(decoded_call) => {
@@ -227,7 +368,7 @@ fn map_uni_calls(blk: &eth::Block, calls: &mut contract::Calls) {
         .transactions()
         .flat_map(|tx| {
             tx.calls.iter()
-                .filter(|call| call.address == UNI_TRACKED_CONTRACT && abi::uni_contract::functions::Mint::match_call(call))
+                .filter(|call| call.address.as_slice() == tracked_contract && abi::uni_contract::functions::Mint::match_call(call))
                 .filter_map(|call| {
                     match abi::uni_contract::functions::Mint::decode(call) {
                         Ok(decoded_call) => {
@@ -250,7 +391,7 @@ fn map_uni_calls(blk: &eth::Block, calls: &mut contract::Calls) {
         .transactions()
         .flat_map(|tx| {
             tx.calls.iter()
-                .filter(|call| call.address == UNI_TRACKED_CONTRACT && abi::uni_contract::functions::Permit::match_call(call))
+                .filter(|call| call.address.as_slice() == tracked_contract && abi::uni_contract::functions::Permit::match_call(call))
                 .filter_map(|call| {
                     match abi::uni_contract::functions::Permit::decode(call) {
                         Ok(decoded_call) => {
@@ -278,7 +419,7 @@ fn map_uni_calls(blk: &eth::Block, calls: &mut contract::Calls) {
         .transactions()
         .flat_map(|tx| {
             tx.calls.iter()
-                .filter(|call| call.address == UNI_TRACKED_CONTRACT && abi::uni_contract::functions::SetMinter::match_call(call))
+                .filter(|call| call.address.as_slice() == tracked_contract && abi::uni_contract::functions::SetMinter::match_call(call))
                 .filter_map(|call| {
                     match abi::uni_contract::functions::SetMinter::decode(call) {
                         Ok(decoded_call) => {
@@ -300,7 +441,7 @@ fn map_uni_calls(blk: &eth::Block, calls: &mut contract::Calls) {
         .transactions()
         .flat_map(|tx| {
             tx.calls.iter()
-                .filter(|call| call.address == UNI_TRACKED_CONTRACT && abi::uni_contract::functions::Transfer::match_call(call))
+                .filter(|call| call.address.as_slice() == tracked_contract && abi::uni_contract::functions::Transfer::match_call(call))
                 .filter_map(|call| {
                     match abi::uni_contract::functions::Transfer::decode(call) {
                         Ok(decoded_call) => {
@@ -329,7 +470,7 @@ fn map_uni_calls(blk: &eth::Block, calls: &mut contract::Calls) {
         .transactions()
         .flat_map(|tx| {
             tx.calls.iter()
-                .filter(|call| call.address == UNI_TRACKED_CONTRACT && abi::uni_contract::functions::TransferFrom::match_call(call))
+                .filter(|call| call.address.as_slice() == tracked_contract && abi::uni_contract::functions::TransferFrom::match_call(call))
                 .filter_map(|call| {
                     match abi::uni_contract::functions::TransferFrom::decode(call) {
                         Ok(decoded_call) => {
@@ -368,15 +509,17 @@ fn map_events_calls(
     })
 }
 #[substreams::handlers::map]
-fn map_events(blk: eth::Block) -> Result<contract::Events, substreams::errors::Error> {
+fn map_events(params: String, blk: eth::Block) -> Result<contract::Events, substreams::errors::Error> {
+    let config = parse_params(&params);
     let mut events = contract::Events::default();
-    map_uni_events(&blk, &mut events);
+    map_uni_events(&blk, &mut events, &config.tracked_contract);
     Ok(events)
 }
 #[substreams::handlers::map]
-fn map_calls(blk: eth::Block) -> Result<contract::Calls, substreams::errors::Error> {
+fn map_calls(params: String, blk: eth::Block) -> Result<contract::Calls, substreams::errors::Error> {
+let config = parse_params(&params);
 let mut calls = contract::Calls::default();
-    map_uni_calls(&blk, &mut calls);
+    map_uni_calls(&blk, &mut calls, &config.tracked_contract);
     Ok(calls)
 }
 
@@ -401,41 +544,217 @@ fn store_wallet_balances(
 }
 
 
+// --- ⛽ STORE MODULE: Wallet Gas Fees (Stateful) ---
+// Accumulates the cumulative fee (in wei) spent by the wallet that actually
+// paid it (the tx origin), so downstream consumers can rank wallets by gas
+// spend the same way the balance store ranks them by holdings. The fee is a
+// per-transaction cost, not a per-transfer one, so a tx emitting several
+// tracked transfers (or one routed via transferFrom, where the token `from`
+// isn't the gas payer) must still only be charged once, to the origin.
+#[substreams::handlers::store]
+fn store_wallet_fees(
+    blk: eth::Block,
+    events: contract::Events,
+    store: substreams::store::StoreAddBigInt,
+) {
+    let tx_fees = build_tx_fee_lookup(&blk);
+    let mut charged_txs: std::collections::HashSet<Vec<u8>> = std::collections::HashSet::new();
+
+    for transfer in events.uni_transfers {
+        let tx_hash = match Hex::decode(&transfer.evt_tx_hash) {
+            Ok(bytes) => bytes,
+            Err(_) => continue,
+        };
+
+        if !charged_txs.insert(tx_hash.clone()) {
+            continue;
+        }
+
+        if let Some((origin, fee)) = tx_fees.get(&tx_hash) {
+            if fee.to_string() != "0" {
+                let store_fee = StoreBigInt::from_str(&fee.to_string()).unwrap_or_default();
+                store.add(&origin.to_hex(), &store_fee);
+            }
+        }
+    }
+}
+
+
+// --- 🗳️ STORE MODULE: Delegate Voting Power (Stateful) ---
+// Tracks each delegate's current voting power, keyed by delegate address,
+// driven directly off `DelegateVotesChanged.new_balance` (the authoritative
+// post-event balance, no need to accumulate deltas ourselves).
+#[substreams::handlers::store]
+fn store_delegate_voting_power(events: contract::Events, store: StoreSetBigInt) {
+    for vote_change in events.uni_delegate_votes_changeds {
+        let new_balance = StoreBigInt::from_str(&vote_change.new_balance).unwrap_or_default();
+        store.set(&vote_change.delegate.to_hex(), &new_balance);
+    }
+}
+
+
+// --- 🗳️ STORE MODULE: Delegator Counts (Stateful) ---
+// For every DelegateChanged, decrements the delegator count of the delegate
+// being moved away from (`from_delegate`, already carried on the event) and
+// increments the count of the delegate being moved to (`to_delegate`), so a
+// delegator switching targets never counts twice. The zero address means
+// "no delegate" (first-time delegation / undelegation) and is skipped.
+#[substreams::handlers::store]
+fn store_delegator_counts(
+    events: contract::Events,
+    store: substreams::store::StoreAddBigInt,
+) {
+    let decrement = StoreBigInt::from_str("-1").unwrap();
+    let increment = StoreBigInt::from_str("1").unwrap();
+
+    for delegate_changed in events.uni_delegate_changeds {
+        if delegate_changed.from_delegate != ZERO_ADDRESS {
+            store.add(&delegate_changed.from_delegate.to_hex(), &decrement);
+        }
+
+        if delegate_changed.to_delegate != ZERO_ADDRESS {
+            store.add(&delegate_changed.to_delegate.to_hex(), &increment);
+        }
+    }
+}
+
+
+// --- 🗳️ STORE MODULE: Delegate Targets (Stateful) ---
+// Queryable delegator -> current delegatee state, as called for by the
+// request. Deliberately NOT consumed by store_delegator_counts: reading it
+// back as a store input there would observe this module's own writes for the
+// current block (stores list each other as already-applied dependencies),
+// which is what made the delegator-count diff wrong before. The event's own
+// from_delegate/to_delegate fields remain the source of truth for that.
+#[substreams::handlers::store]
+fn store_delegate_targets(events: contract::Events, store: StoreSetRaw) {
+    for delegate_changed in events.uni_delegate_changeds {
+        store.set(&delegate_changed.delegator.to_hex(), &delegate_changed.to_delegate);
+    }
+}
+
+
+// --- 🪙 STORE MODULE: Circulating Supply (Stateful) ---
+// Single running total driven by actual issuance/burn activity instead of
+// balance deltas (which always net to zero across a transfer). Increases on
+// transfers out of the zero address (mints), decreases on transfers into the
+// zero address (burns).
+//
+// This total is only correct if indexing starts at the tracked contract's
+// deployment block, so every mint/burn is observed from zero. This module
+// has no way to seed a non-zero starting total itself (no block-0 hook, no
+// params input here), so the manifest MUST pin this module's `initialBlock`
+// (and every module feeding from it) to that deployment block; starting
+// later under-counts supply and can drive it negative if burns are observed
+// without their matching mint. `percentageOfSupply` below clamps against
+// that rather than trusting the raw total.
+#[substreams::handlers::store]
+fn store_circulating_supply(events: contract::Events, store: substreams::store::StoreAddBigInt) {
+    // `Mint` calls are deliberately not counted separately here: UNI's
+    // `mint(...)` always also emits `Transfer(address(0), dst, amount)`, so
+    // the zero-address transfer branch below already covers every mint path.
+    // Counting both would double the tracked issuance.
+    for transfer in &events.uni_transfers {
+        let amount = StoreBigInt::from_str(&transfer.amount).unwrap_or_default();
+
+        if transfer.from == ZERO_ADDRESS {
+            store.add(CIRCULATING_SUPPLY_KEY, &amount);
+        } else if transfer.to == ZERO_ADDRESS {
+            store.add(CIRCULATING_SUPPLY_KEY, &amount.neg());
+        }
+    }
+}
 
 
 #[substreams::handlers::map]
 fn graph_out(
+    // Input 0: Module params (tracked contract address)
+    params: String,
     // Input 1: Filtered whale transfers (from map_whale_transfers)
-    whale_transfers: contract::UniTransfers, 
+    whale_transfers: contract::UniTransfers,
     // Input 2: Wallet balance deltas (from store_wallet_balances)
-    wallet_deltas: substreams::store::Deltas<StoreBigInt> 
+    wallet_deltas: substreams::store::Deltas<StoreBigInt>,
+    // Input 3: Cumulative wallet gas-fee deltas (from store_wallet_fees)
+    wallet_fee_deltas: substreams::store::Deltas<StoreBigInt>,
+    // Input 4: Raw governance events, used to emit Delegation link entities
+    events: contract::Events,
+    // Input 5: Delegate voting power deltas (from store_delegate_voting_power)
+    voting_power_deltas: substreams::store::Deltas<StoreBigInt>,
+    // Input 6: Delegate delegator-count deltas (from store_delegator_counts)
+    delegator_count_deltas: substreams::store::Deltas<StoreBigInt>,
+    // Input 7: Current circulating supply (from store_circulating_supply)
+    circulating_supply: substreams::store::StoreGetBigInt,
+    // Input 8: Circulating supply deltas, used to drive the Token entity update
+    circulating_supply_deltas: substreams::store::Deltas<StoreBigInt>
 ) -> Result<EntityChanges, substreams::errors::Error> {
-    
+
     let mut entity_changes: EntityChanges = Default::default();
-    
-    // The Token ID is constant (the UNI contract address)
-    let token_id = Hex(super::UNI_TRACKED_CONTRACT).to_string();
+
+    // The Token ID is the configured contract address, not a compiled-in constant.
+    let config = parse_params(&params);
+    let token_id = Hex(&config.tracked_contract).to_string();
 
     // ==========================================================
     // 1. WALLET and TOKEN Updates (Concentration & Supply)
     // ==========================================================
     
-    for delta in wallet_deltas.deltas {
-        let wallet_id = delta.key.clone();
-        
+    // A wallet can move tokens more than once in a block, so store_wallet_balances
+    // emits one delta per add(). netValue is inbound - outbound *over the whole
+    // block*, so we aggregate (new - old) across every delta for a given wallet
+    // (equivalent to last delta's new_value - first delta's old_value) before
+    // emitting a single Wallet update, instead of letting the last delta win.
+    let mut wallet_order: Vec<String> = Vec::new();
+    let mut wallet_net_value: HashMap<String, BigInt> = HashMap::new();
+    let mut wallet_last_delta: HashMap<String, substreams::store::Delta<StoreBigInt>> = HashMap::new();
+
+    for delta in wallet_deltas.deltas.iter() {
+        let old_val = BigInt::from_str(&delta.old_value.to_string()).unwrap_or_default();
+        let new_val = BigInt::from_str(&delta.new_value.to_string()).unwrap_or_default();
+
+        if !wallet_net_value.contains_key(&delta.key) {
+            wallet_order.push(delta.key.clone());
+        }
+
+        *wallet_net_value.entry(delta.key.clone()).or_insert_with(BigInt::default) += new_val - old_val;
+        wallet_last_delta.insert(delta.key.clone(), delta.clone());
+    }
+
+    for wallet_id in wallet_order {
+        let delta = &wallet_last_delta[&wallet_id];
+
         // Use delta.new_value for the current balance
         let balance_str = delta.new_value.to_string();
-        
+
+        let net_value = wallet_net_value[&wallet_id].to_string();
+
         // Determine if we should CREATE or UPDATE the Wallet entity
         let operation = match delta.operation() {
             // If the key is created or updated, we use Update. The Subgraph sink handles
             // CREATE/UPDATE correctly based on existence.
             substreams::store::DeltaOperation::Update | substreams::store::DeltaOperation::Create => Operation::Update,
-            
+
             // If the balance goes to zero and the key is deleted, you might choose to delete the entity.
             _ => Operation::Update,
         };
-        
+
+        // percentageOfSupply reads the authoritative circulating supply (tracked
+        // by store_circulating_supply from real mint/burn activity) rather than
+        // the wallet balance store, since summing balance deltas nets to zero
+        // across every transfer and never reflects true issuance/burn.
+        let supply = circulating_supply.get_last(CIRCULATING_SUPPLY_KEY).unwrap_or_default();
+        let supply_bigint = BigInt::from_str(&supply.to_string()).unwrap_or_default();
+        // Clamp to 0 rather than 0-check alone: if indexing started after the
+        // tracked contract's deployment block (see store_circulating_supply),
+        // observed burns without their matching mint can drive the running
+        // total negative, which would otherwise surface as a negative or
+        // meaningless percentageOfSupply.
+        let percentage_of_supply = if supply_bigint <= BigInt::from(0) {
+            BigDecimal::from(0)
+        } else {
+            BigDecimal::from_str(&balance_str).unwrap_or_default()
+                / BigDecimal::from_str(&supply.to_string()).unwrap_or_default()
+        };
+
         // --- A. Update/Create WALLET Entity ---
         entity_changes.push_change(
             "Wallet",
@@ -445,38 +764,41 @@ fn graph_out(
             &[
                 // Set the current balance based on the store output
                 ("balance", balance_str.clone()),
+                // Net token flow (inbound - outbound) realized during this block
+                ("netValue", net_value),
+                ("percentageOfSupply", percentage_of_supply.with_prec(18).to_string()),
                 // Link the Wallet to the main Token entity
                 ("token", token_id.clone()),
-                // NOTE: percentageOfSupply is complex and usually requires the circulatingSupply 
-                // value, which should ideally come from a separate store module (omitted for brevity).
             ]
         );
-        
-        // --- B. Update TOKEN Entity (Circulating Supply) ---
-        // Calculate the change in circulating supply using the delta
-        // if let Ok(amount) = StoreBigInt::from_str(&delta.old_value.to_string()) {
-           //  let circulating_supply_change = StoreBigInt::from_str(&delta.new_value.to_string()).unwrap_or_default() - amount;
-	let old_balance = BigInt::from_str(&delta.old_value.to_string()).unwrap_or_default();
-	let new_balance = BigInt::from_str(&delta.new_value.to_string()).unwrap_or_default();
-
-	// Calculate the net change: new - old
-	let circulating_supply_change = new_balance - old_balance;
-	
-	if circulating_supply_change.to_string() != "0" {
-             // This pushes a change to the TOKEN entity that aggregates the balance changes.
-             // Subgraph sink handles this complex aggregation.
-             entity_changes.push_change(
-                "Token",
-                &token_id,
-                delta.ordinal(),
-                Operation::Update,
-                &[
-                    // This tells the sink to ADD the change in token balance to the circulatingSupply field.
-                    // This is a powerful, non-standard feature of Substreams Entity Changes.
-                    ("circulatingSupply", circulating_supply_change.to_string())
-                ]
-             );
-        }
+    }
+
+    // --- B. Update TOKEN Entity (Circulating Supply) ---
+    // Driven by the authoritative store_circulating_supply module, not by
+    // aggregating wallet balance deltas (which always nets to zero).
+    for delta in circulating_supply_deltas.deltas {
+        entity_changes.push_change(
+            "Token",
+            &token_id,
+            delta.ordinal(),
+            Operation::Update,
+            &[
+                ("circulatingSupply", delta.new_value.to_string()),
+            ]
+        );
+    }
+
+    // --- C. Update WALLET Entity with cumulative gas fees spent ---
+    for fee_delta in wallet_fee_deltas.deltas {
+        entity_changes.push_change(
+            "Wallet",
+            &fee_delta.key,
+            fee_delta.ordinal(),
+            Operation::Update,
+            &[
+                ("totalFeesPaidWei", fee_delta.new_value.to_string()),
+            ]
+        );
     }
 
     // ==========================================================
@@ -500,6 +822,8 @@ fn graph_out(
                 ("toWallet", Hex::encode(&transfer.to)),
                 // Transfer Details
                 ("amount", transfer.amount), // The BigInt amount (as string)
+                ("feePaidWei", transfer.fee_paid_wei.clone()),
+                ("tier", transfer.tier.clone()),
                 ("timestamp", transfer.evt_block_time.map(|t| t.seconds).unwrap_or_default().to_string()),
                 ("txHash", transfer.evt_tx_hash.clone()),
                 // ("amountUSD", transfer.amount_usd) // Requires an external price oracle module (complex, often omitted for hackathons)
@@ -507,8 +831,53 @@ fn graph_out(
         );
     }
 
-    // 3. LIQUIDITY POOL AND LOCKS
-    // NOTE: Logic for LiquidityPool and LiquidityLock entities must be added here 
+    // ==========================================================
+    // 3. DELEGATE and DELEGATION entities (Governance Concentration)
+    // ==========================================================
+
+    for delta in voting_power_deltas.deltas {
+        entity_changes.push_change(
+            "Delegate",
+            &delta.key,
+            delta.ordinal(),
+            Operation::Update,
+            &[
+                ("votingPower", delta.new_value.to_string()),
+            ]
+        );
+    }
+
+    for delta in delegator_count_deltas.deltas {
+        entity_changes.push_change(
+            "Delegate",
+            &delta.key,
+            delta.ordinal(),
+            Operation::Update,
+            &[
+                ("delegatorCount", delta.new_value.to_string()),
+            ]
+        );
+    }
+
+    for delegate_changed in events.uni_delegate_changeds {
+        let id = format!("{}-{}", delegate_changed.evt_tx_hash, delegate_changed.evt_index);
+
+        entity_changes.push_change(
+            "Delegation",
+            &id,
+            delegate_changed.evt_index,
+            Operation::Create,
+            &[
+                ("delegator", Hex::encode(&delegate_changed.delegator)),
+                ("delegatee", Hex::encode(&delegate_changed.to_delegate)),
+                ("txHash", delegate_changed.evt_tx_hash.clone()),
+                ("timestamp", delegate_changed.evt_block_time.map(|t| t.seconds).unwrap_or_default().to_string()),
+            ]
+        );
+    }
+
+    // 4. LIQUIDITY POOL AND LOCKS
+    // NOTE: Logic for LiquidityPool and LiquidityLock entities must be added here
     // after you implement the modules for the Factory/Locker contracts in your substreams.yaml.
 
     Ok(entity_changes)
@@ -517,21 +886,32 @@ fn graph_out(
 
 #[substreams::handlers::map]
 fn map_whale_transfers(
-    // Input is the output of map_events (containing ALL transfers)
+    // Input 0: Module params (tiered whale thresholds)
+    params: String,
+    // Input 1: The output of map_events (containing ALL transfers)
     events: contract::Events
 ) -> Result<contract::UniTransfers, substreams::errors::Error> {
-    
+
+    let config = parse_params(&params);
     let mut whale_transfers = contract::UniTransfers::default();
 
-    for transfer in events.uni_transfers {
+    // The lowest configured tier (e.g. "shrimp") is noise for an entity named
+    // WhaleTransfer — emitting it for every dust-sized transfer would bury the
+    // whale signal the entity exists to surface. Still classify every
+    // transfer into its tier, just don't emit the floor tier as an entity.
+    let floor_tier = config.whale_tiers.first().map(|t| t.label.clone());
+
+    for mut transfer in events.uni_transfers {
         // 1. Parse the amount string back into a BigInt for comparison
         let amount_bigint = num_bigint::BigInt::from_str(&transfer.amount).unwrap_or_default();
-        
-        // 2. APPLY THE WHALE THRESHOLD
-        if amount_bigint.ge(&WHALE_THRESHOLD) {
-            // Keep the transfer and push it to the output stream
-            whale_transfers.uni_transfers.push(transfer);
+
+        // 2. Classify into a tier instead of a boolean keep/drop
+        transfer.tier = classify_tier(&amount_bigint, &config.whale_tiers);
+
+        if Some(&transfer.tier) == floor_tier.as_ref() {
+            continue;
         }
+        whale_transfers.uni_transfers.push(transfer);
     }
 
     Ok(whale_transfers)